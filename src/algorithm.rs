@@ -3,13 +3,303 @@
 //! in an arbitrage opportunity.
 //!
 //! The approach is based on Bellman-Ford minimum cost path search.
-//! We can apply it because the initial rates we get from the API are normalized
-//! in base 10**8 (based on the assumption that the rates have 8 decimals always),
-//! and then transformed into negative logharitms so that adding them (for min cost
-//! path finding) is equivalent to multiplying the underlying rates for multiple
-//! pairs, which is relevant for finding arbitrage opportunities.
+//! Rates are transformed into negative logarithms so that adding them (for
+//! min cost path finding) is equivalent to multiplying the underlying rates
+//! for multiple pairs, which is relevant for finding arbitrage opportunities.
+//! This relaxation only needs to be fast, so it works off an `f64` downcast
+//! of the exact `BigDecimal` rates stored in `Graph`; whether a discovered
+//! cycle's rates grow the trade is re-checked separately from the exact
+//! rates, so `f64` rounding can't make a cycle pass that check falsely.
+//! Candidate cycles themselves are still found with the `f64` weights, so a
+//! cycle within `f64` rounding of break-even can be missed.
 
+use crate::pairs::{FeeConfig, Graph};
+use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
 use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+/// Fee factor `gamma = 1 - swap_fee` applied by constant-product AMM pools
+/// when collapsing a chain of pools (e.g. `0.997` for Uniswap's 0.3% swap
+/// fee). This is distinct from `FeeConfig`, which models per-pair taker fees
+/// on order-book venues.
+pub const DEFAULT_AMM_GAMMA: f64 = 0.997;
+
+/// Above this many tokens, `trades` falling back to an exhaustive,
+/// `max_hops = n` DFS the moment any negative cycle exists anywhere (see
+/// `ArbitrageIteration::trades`) stops being practical: the search is
+/// combinatorial in the token count, fine for the challenge API's handful of
+/// tokens but not for anything resembling a real exchange's full pair
+/// matrix. Past this ceiling, `trades` caps the search at
+/// `TRADES_BOUNDED_MAX_HOPS` hops instead of every simple cycle, trading
+/// completeness for staying usable, and logs a warning so the tradeoff
+/// isn't silent.
+const TRADES_TOKEN_CEILING: usize = 12;
+
+/// The hop bound `trades` falls back to once `TRADES_TOKEN_CEILING` is
+/// crossed, matching the `dfs` solver's own CLI default (see
+/// `Args::max_hops`). Callers who need every cycle on a larger graph should
+/// use the `dfs` solver directly with an explicit `--max-hops`.
+const TRADES_BOUNDED_MAX_HOPS: usize = 4;
+
+/// Collapses a chain of constant-product pools, one per hop of a cycle, into
+/// a single equivalent pool with effective reserves `(e_in, e_out)`, folding
+/// left-to-right: pool1 `(a, b)` and pool2 `(b', c)` combine into
+/// `e_a = a*b' / (b' + gamma*b)` and `e_c = gamma*b*c / (b' + gamma*b)`.
+fn collapse_pools(pools: &[(f64, f64)], gamma: f64) -> Option<(f64, f64)> {
+    let mut pools = pools.iter();
+    let (mut e_in, mut e_out) = *pools.next()?;
+    for &(b_prime, c) in pools {
+        let denom = b_prime + gamma * e_out;
+        if denom <= 0.0 {
+            return None;
+        }
+        let new_e_in = e_in * b_prime / denom;
+        let new_e_out = gamma * e_out * c / denom;
+        e_in = new_e_in;
+        e_out = new_e_out;
+    }
+    Some((e_in, e_out))
+}
+
+/// Given the effective reserves of a collapsed pool, computes the
+/// profit-maximizing input `x*` and the resulting profit. Returns `None` when
+/// no profitable input exists (`x* <= 0`).
+fn optimal_trade(e_in: f64, e_out: f64, gamma: f64) -> Option<(f64, f64)> {
+    let x_star = ((gamma * e_in * e_out).sqrt() - e_in) / gamma;
+    if x_star <= 0.0 {
+        return None;
+    }
+    let output = e_out * gamma * x_star / (e_in + gamma * x_star);
+    Some((x_star, output - x_star))
+}
+
+/// Recomputes the amount realized by trading `trade_amount` around `cycle`
+/// (a token sequence with the starting token repeated at the end), applying
+/// per-pair fees using the exact `BigDecimal` rates so the final
+/// accept/reject decision isn't subject to `f64` rounding near the
+/// break-even boundary. The fast `f64` log-weights are only used to find
+/// candidate cycles; the returned `bool` (whether the cycle actually grew
+/// the trade) decides whether one is reported, unless its pool reserves
+/// admit a profitable optimal trade (see `arbitrage_message`).
+fn exact_new_amount(
+    cycle: &[&String],
+    graph: &Graph,
+    trade_amount: u64,
+    fees: &FeeConfig,
+) -> (BigDecimal, bool) {
+    let rates = graph.as_ref();
+    let start_amount = BigDecimal::from(trade_amount);
+    let mut amount = start_amount.clone();
+    for idx in 0..cycle.len() - 1 {
+        let first_token = cycle[idx];
+        let second_token = cycle[idx + 1];
+        let rate = rates[first_token][second_token].clone();
+        let gross = amount * rate;
+        let fee_rate = BigDecimal::from_f64(fees.fee_for(first_token, second_token))
+            .unwrap_or_else(|| BigDecimal::from(0));
+        let min_fee =
+            BigDecimal::from_f64(fees.min_fee).unwrap_or_else(|| BigDecimal::from(0));
+        let fee_amount = (gross.clone() * fee_rate).max(min_fee);
+        amount = gross - fee_amount;
+    }
+    let is_profitable = amount > start_amount;
+    (amount, is_profitable)
+}
+
+/// Reports an arbitrage message for `cycle` when either the exact
+/// fee-adjusted amount exceeds the input or the cycle's pools admit a
+/// profitable optimal trade size. Shared by both the Bellman-Ford and DFS
+/// solvers so a cycle is scored identically regardless of how it was found.
+fn arbitrage_message(
+    cycle: &[&String],
+    graph: &Graph,
+    trade_amount: u64,
+    fees: &FeeConfig,
+) -> Option<String> {
+    let (exact_amount, is_profitable) = exact_new_amount(cycle, graph, trade_amount, fees);
+    let new_amount = exact_amount.to_f64().unwrap_or(0.0);
+
+    let pools: Vec<Option<(f64, f64)>> = (0..cycle.len() - 1)
+        .map(|idx| graph.reserves_for(cycle[idx], cycle[idx + 1]))
+        .collect();
+
+    // When every hop in the cycle has known pool reserves, also report the
+    // profit-maximizing input size, computed by collapsing the chain of
+    // constant-product pools into a single equivalent pool.
+    let optimal = pools
+        .into_iter()
+        .collect::<Option<Vec<(f64, f64)>>>()
+        .and_then(|pools| collapse_pools(&pools, DEFAULT_AMM_GAMMA))
+        .and_then(|(e_in, e_out)| optimal_trade(e_in, e_out, DEFAULT_AMM_GAMMA));
+
+    if !is_profitable && optimal.is_none() {
+        return None;
+    }
+
+    let path = cycle
+        .iter()
+        .map(|token| token.as_str())
+        .collect::<Vec<&str>>()
+        .join(" <--> ");
+
+    Some(match optimal {
+        Some((x_star, profit)) => format!(
+            "Arbitrage opportunity: {}, new trade amount is {:.8}, \
+             optimal input is {:.8} with expected profit {:.8}",
+            path, new_amount, x_star, profit
+        ),
+        None => format!(
+            "Arbitrage opportunity: {}, new trade amount is {:.8}",
+            path, new_amount
+        ),
+    })
+}
+
+/// Canonicalizes a cycle (a token sequence with the starting token repeated
+/// at the end) by rotating it to start at its lexicographically smallest
+/// token, so that e.g. `A <--> B <--> C <--> A` and `B <--> C <--> A <--> B`
+/// -- the same directed cycle, discovered starting from different vertices
+/// -- dedupe to the same cycle.
+///
+/// This deliberately does *not* also fold in the cycle's reversal: on a
+/// directed graph, `A -> B -> C -> A` and its reversal `A -> C -> B -> A`
+/// are different edges entirely (and can have entirely different, even
+/// independently profitable, rates), so collapsing them together would
+/// silently drop a real, distinct arbitrage opportunity depending on
+/// whichever direction the DFS happened to reach first.
+fn canonical_cycle<'a>(cycle: &[&'a String]) -> Vec<&'a String> {
+    let core = &cycle[..cycle.len() - 1];
+    let n = core.len();
+    let min_idx = (0..n).min_by_key(|&i| core[i]).unwrap();
+    (0..n).map(|i| core[(min_idx + i) % n]).collect()
+}
+
+/// Enumerates every distinct negative-weight cycle of length at most
+/// `max_hops` via DFS over the log-negated graph, as an alternative to the
+/// single-source Bellman-Ford relaxation used by `trades`. This bounds hop
+/// count explicitly, which Bellman-Ford cannot do, and is useful when route
+/// length matters (e.g. gas/step-sensitive on-chain arbitrage).
+pub fn dfs_trades(
+    log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+    max_hops: usize,
+    trade_amount: u64,
+    graph: &Graph,
+    fees: &FeeConfig,
+) -> Trades {
+    let mut tokens: Vec<&String> = log_negated_graph.keys().collect();
+    tokens.sort();
+
+    let mut canonical_seen = HashSet::new();
+    let mut cycles = Vec::new();
+    for start in &tokens {
+        let mut path = vec![*start];
+        let mut visited = HashSet::new();
+        visited.insert(*start);
+        dfs_visit(
+            start,
+            log_negated_graph,
+            max_hops,
+            0.0,
+            &mut path,
+            &mut visited,
+            &mut cycles,
+            &mut canonical_seen,
+        );
+    }
+
+    let mut arbitrage_paths = HashSet::new();
+    for cycle in &cycles {
+        if let Some(message) = arbitrage_message(cycle, graph, trade_amount, fees) {
+            arbitrage_paths.insert(message);
+        }
+    }
+
+    let mut arbitrage: Vec<String> = arbitrage_paths.into_iter().collect();
+    arbitrage.sort();
+    Trades { arbitrage }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn dfs_visit<'a>(
+    start: &'a String,
+    log_negated_graph: &'a HashMap<String, HashMap<String, f64>>,
+    max_hops: usize,
+    acc_weight: f64,
+    path: &mut Vec<&'a String>,
+    visited: &mut HashSet<&'a String>,
+    cycles: &mut Vec<Vec<&'a String>>,
+    canonical_seen: &mut HashSet<Vec<&'a String>>,
+) {
+    let current = *path.last().unwrap();
+
+    // Closing the cycle back to `start` is only considered once we've taken
+    // at least one hop. A simple cycle is recorded here when its summed
+    // log-weight is at most zero, i.e. breakeven or better on rate alone:
+    // a breakeven cycle can still be genuinely profitable once
+    // `arbitrage_message` accounts for pool reserves, so that makes the
+    // final accept/reject decision. A cycle whose summed log-weight is
+    // positive is not recorded, even if its pools would admit a profitable
+    // trade.
+    if path.len() > 1 {
+        if let Some(weight) = log_negated_graph[current].get(start) {
+            if acc_weight + weight <= 0.0 {
+                let mut cycle = path.clone();
+                cycle.push(start);
+                let canonical = canonical_cycle(&cycle);
+                if canonical_seen.insert(canonical) {
+                    cycles.push(cycle);
+                }
+            }
+        }
+    }
+
+    // `path.len()` is the number of edges a cycle closing from `current`
+    // back to `start` would have, so stop extending once it has already
+    // reached `max_hops`: extending once more would only ever be able to
+    // close a cycle one hop longer than allowed.
+    if path.len() >= max_hops {
+        return;
+    }
+
+    for (next, weight) in &log_negated_graph[current] {
+        if next == current || next == start || visited.contains(next) {
+            continue;
+        }
+
+        visited.insert(next);
+        path.push(next);
+        dfs_visit(
+            start,
+            log_negated_graph,
+            max_hops,
+            acc_weight + weight,
+            path,
+            visited,
+            cycles,
+            canonical_seen,
+        );
+        path.pop();
+        visited.remove(next);
+    }
+}
+
+/// Looks up the log-negated weight of the directed edge `from -> to`,
+/// treating a missing entry the same as the existing `f64::MAX` sentinel for
+/// "no usable rate" rather than panicking. Pair data is only ever complete
+/// for the challenge API's full-matrix shape; AMM-sourced pairs (see
+/// `Graph::reserves_for`) are sparse by nature, so every relaxation loop in
+/// this module needs to tolerate a token pair simply not existing.
+fn edge_weight(
+    log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+    from: &str,
+    to: &str,
+) -> f64 {
+    log_negated_graph
+        .get(from)
+        .and_then(|edges| edges.get(to))
+        .copied()
+        .unwrap_or(f64::MAX)
+}
 
 #[derive(Debug)]
 pub struct Trades {
@@ -31,133 +321,344 @@ impl<'a> ArbitrageIteration<'a> {
         }
     }
 
-    /// Computed for all starting points.
+    /// Populates `indices_map` from the graph's token set, sorted so that
+    /// the mapping from token to index is stable across runs (otherwise
+    /// results would vary from run to run, which is hard to test).
+    fn setup_indices(&mut self, log_negated_graph: &'a HashMap<String, HashMap<String, f64>>) {
+        self.indices_map = log_negated_graph.keys().collect();
+        self.indices_map.sort();
+    }
+
+    fn token_index(&self, token: &str) -> Option<usize> {
+        self.indices_map.iter().position(|t| t.as_str() == token)
+    }
+
+    /// One round of Bellman-Ford relaxation over every directed pair,
+    /// skipping any pair `edge_weight` reports as unusable. `scorer` lets
+    /// callers reweight edges while relaxing (e.g. penalizing low-liquidity
+    /// hops) without duplicating this loop. Returns whether anything was
+    /// relaxed, so callers can detect a negative-weight cycle by checking
+    /// whether a round past the expected `n - 1` still relaxes something.
+    fn relax_round(
+        &mut self,
+        log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+        scorer: &Scorer,
+    ) -> bool {
+        let n = self.min_dist.len();
+        let mut relaxed = false;
+        for source_curr in 0..n {
+            // A vertex that's still unreached can't usefully relax anything
+            // else yet.
+            if self.min_dist[source_curr] == f64::MAX {
+                continue;
+            }
+            for dest_curr in 0..n {
+                if source_curr == dest_curr {
+                    continue;
+                }
+
+                let raw_weight = edge_weight(
+                    log_negated_graph,
+                    self.indices_map[source_curr],
+                    self.indices_map[dest_curr],
+                );
+                if raw_weight == f64::MAX {
+                    continue;
+                }
+
+                let weight = scorer(
+                    self.indices_map[source_curr],
+                    self.indices_map[dest_curr],
+                    raw_weight,
+                );
+                let candidate = self.min_dist[source_curr] + weight;
+                if self.min_dist[dest_curr] > candidate {
+                    self.min_dist[dest_curr] = candidate;
+                    self.pre[dest_curr] = source_curr as i32;
+                    relaxed = true;
+                }
+            }
+        }
+        relaxed
+    }
+
+    /// Runs `n - 1` rounds of `relax_round` from `source_idx`, the number of
+    /// rounds needed to investigate paths up to `n - 1` edges long, since the
+    /// longest path in a graph with `n` vertices has `n - 1` edges.
+    fn relax_from(
+        &mut self,
+        log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+        source_idx: usize,
+        scorer: &Scorer,
+    ) {
+        let n = self.min_dist.len();
+        self.min_dist[source_idx] = 0f64;
+        for _ in 0..n.saturating_sub(1) {
+            self.relax_round(log_negated_graph, scorer);
+        }
+    }
+
+    /// Seeds every vertex at distance zero (the standard "virtual
+    /// super-source" trick) rather than picking one arbitrary root, so the
+    /// relaxation that `trades` checks afterwards notices a negative-weight
+    /// cycle no matter which vertex happens to reach it.
     pub fn compute_arbitrage_opportunities(
         &mut self,
         log_negated_graph: &'a HashMap<String, HashMap<String, f64>>,
     ) {
-        // Setup.
-        let n = log_negated_graph.keys().len();
-        self.indices_map = log_negated_graph.keys().collect();
-        // We sort the indices map because otherwise will return different results for
-        // different runs, which are hard to test.
-        self.indices_map.sort();
+        self.setup_indices(log_negated_graph);
+        let n = self.min_dist.len();
+        self.min_dist.fill(0.0);
+        for _ in 0..n.saturating_sub(1) {
+            self.relax_round(log_negated_graph, &default_scorer);
+        }
+    }
+
+    /// Read-only counterpart to `relax_round`: reports whether another round
+    /// would still relax something, without requiring a mutable borrow of
+    /// `self`. Used by `trades` to check for a negative-weight cycle after
+    /// `compute_arbitrage_opportunities`.
+    fn is_relaxable(
+        &self,
+        log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+        scorer: &Scorer,
+    ) -> bool {
+        let n = self.min_dist.len();
+        for source_curr in 0..n {
+            if self.min_dist[source_curr] == f64::MAX {
+                continue;
+            }
+            for dest_curr in 0..n {
+                if source_curr == dest_curr {
+                    continue;
+                }
 
-        // We consider the source being the token associated to index 0. This will be used
-        // when we map the tokens (string representation) to indices so that we can use
-        // vectors instead of hashmaps. This simplifies a bit the mental model of applying
-        // the algorithm. Also, which source we pick shouldn't matter for finding the arbitrage
-        // opportunities, since we have an undirected graph which is complete (considering the
-        // data we get for the pairs);
-        self.min_dist[0] = 0f64;
-
-        // The algorithm needs to iterate for n - 1 times so that paths up to n - 1 edges are
-        // investigated. This is because the longest path in a graph with n vertices has n - 1 edges.
-        for _ in 0..n - 1 {
-            for source_curr in 0..n {
-                for dest_curr in 0..n {
-                    // We want to skip the iteration if source equals with the destination since it
-                    // doesn't make sense to convert a token to itself, or if we discover that a pair has
-                    // a rate which is unrealistically high, which indicates there is something off with the
-                    // data.
-                    if source_curr == dest_curr
-                        || log_negated_graph[self.indices_map[source_curr]]
-                            [self.indices_map[dest_curr]]
-                            == f64::MAX
-                    {
-                        continue;
-                    }
-
-                    // We try to relax the distance to current destination through current source and
-                    // the pair rate between source and destination.
-                    if self.min_dist[dest_curr]
-                        > self.min_dist[source_curr]
-                            + log_negated_graph[self.indices_map[source_curr]]
-                                [self.indices_map[dest_curr]]
-                    {
-                        self.min_dist[dest_curr] = self.min_dist[source_curr]
-                            + log_negated_graph[self.indices_map[source_curr]]
-                                [self.indices_map[dest_curr]];
-                        self.pre[dest_curr] = source_curr as i32;
-                    }
+                let raw_weight = edge_weight(
+                    log_negated_graph,
+                    self.indices_map[source_curr],
+                    self.indices_map[dest_curr],
+                );
+                if raw_weight == f64::MAX {
+                    continue;
+                }
+
+                let weight = scorer(
+                    self.indices_map[source_curr],
+                    self.indices_map[dest_curr],
+                    raw_weight,
+                );
+                if self.min_dist[dest_curr] > self.min_dist[source_curr] + weight {
+                    return true;
                 }
             }
         }
+        false
     }
 
     /// Check for all negative weight cycles, meaning all circular trades
     /// which have the potential of growing the profit continously.
+    ///
+    /// A single predecessor tree, however it's rooted, names at most one
+    /// predecessor per vertex, so it can't represent multiple distinct
+    /// cycles that happen to share an edge (e.g. `BORG <--> EUR <--> DAI
+    /// <--> BORG` and `BTC <--> EUR <--> DAI <--> BTC` both routed through
+    /// the same profitable `EUR -> DAI` hop): recovering a cycle by walking
+    /// `pre` only ever gets back the one the walk happens to land on. So
+    /// Bellman-Ford is only used here as a cheap existence check -- `self`
+    /// having been relaxed from every vertex at once by
+    /// `compute_arbitrage_opportunities`, one more relaxable round means a
+    /// negative-weight cycle exists somewhere in the graph -- and the
+    /// actual enumeration is handed off to `dfs_trades`, which is exhaustive
+    /// by construction, bounded to `n` hops, the longest a simple cycle can
+    /// be (one visiting every vertex). Past `TRADES_TOKEN_CEILING` tokens
+    /// that exhaustive search stops being practical, so the bound is capped
+    /// to `TRADES_BOUNDED_MAX_HOPS` instead, with a warning logged so the
+    /// lost completeness isn't silent.
     pub fn trades(
         &self,
         log_negated_graph: &HashMap<String, HashMap<String, f64>>,
         trade_amount: u64,
-        graph: &HashMap<String, HashMap<String, u64>>,
+        graph: &Graph,
+        fees: &FeeConfig,
     ) -> Trades {
-        let n = self.min_dist.len();
-        let mut paths = HashSet::new();
-        let mut arbitrage_paths = HashSet::new();
-        for mut source_curr in 0..n {
-            for dest_curr in 0..n {
-                // This check confirms this vertices are part of a negative weight cycle.
-                if self.min_dist[dest_curr]
-                    > self.min_dist[source_curr]
-                        + log_negated_graph[self.indices_map[source_curr]]
-                            [self.indices_map[dest_curr]]
-                {
-                    // Construct the cycle in reverse order.
-                    let mut print_cycle = vec![dest_curr];
-                    while !print_cycle.contains(&(self.pre[source_curr] as usize)) {
-                        source_curr = self.pre[source_curr] as usize;
-                        print_cycle.push(source_curr);
-                    }
-                    print_cycle.push(dest_curr);
-
-                    let path = print_cycle
-                        .iter()
-                        .map(|idx| self.indices_map[*idx].to_owned())
-                        .collect::<Vec<String>>()
-                        .join(" <--> ");
-
-                    // Given the rates data is a complete graph, we can end up finding the same minimum path
-                    // (aka the maximum multiplication of rates) for multiple times, so we want to print it
-                    // once.
-                    if !paths.contains(&path) {
-                        let mut new_amount = trade_amount as f64;
-                        for idx in 0..print_cycle.len() - 1 {
-                            let first_token = self.indices_map[print_cycle[idx]];
-                            let second_token = self.indices_map[print_cycle[idx + 1]];
-                            let rate = graph[first_token][second_token];
-                            new_amount *= rate as f64 / 100000000f64;
-                        }
-
-                        if new_amount > trade_amount as f64 {
-                            arbitrage_paths.insert(format!(
-                                "Arbitrage opportunity: {}, new trade amount is {:.8}",
-                                path, new_amount
-                            ));
-                        }
-
-                        paths.insert(path);
-                    }
-                }
-            }
+        if !self.is_relaxable(log_negated_graph, &default_scorer) {
+            return Trades { arbitrage: Vec::new() };
+        }
+
+        let tokens_count = self.min_dist.len();
+        let max_hops = if tokens_count > TRADES_TOKEN_CEILING {
+            tracing::warn!(
+                tokens_count,
+                ceiling = TRADES_TOKEN_CEILING,
+                bounded_max_hops = TRADES_BOUNDED_MAX_HOPS,
+                "token count exceeds the default solver's exhaustive-search ceiling; \
+                 capping to a bounded DFS instead of enumerating every simple cycle. \
+                 Use --solver dfs --max-hops for an explicit, uncapped search."
+            );
+            TRADES_BOUNDED_MAX_HOPS
+        } else {
+            tokens_count
+        };
+
+        dfs_trades(log_negated_graph, max_hops, trade_amount, graph, fees)
+    }
+
+    /// Runs a single-source Bellman-Ford relaxation from `source` for
+    /// `best_route`'s point-to-point search, using `scorer` to weight edges.
+    /// Unlike `compute_arbitrage_opportunities`, this cares which vertex is
+    /// the source: it reports `RouteError::NegativeCycle` only when a
+    /// negative-weight cycle is actually reachable from it, rather than
+    /// refusing to route whenever one exists anywhere in the graph.
+    ///
+    /// Bellman-Ford (rather than Dijkstra) is required here because a
+    /// genuine arbitrage opportunity *is* a negative edge weight in this
+    /// graph, and Dijkstra's non-negative-weight assumption would silently
+    /// skip routes through it once a lower-cost, non-negative alternative
+    /// had already been explored.
+    pub fn compute_shortest_paths(
+        &mut self,
+        log_negated_graph: &'a HashMap<String, HashMap<String, f64>>,
+        source: &str,
+        scorer: &Scorer,
+    ) -> Result<(), RouteError> {
+        self.setup_indices(log_negated_graph);
+        let source_idx = self
+            .token_index(source)
+            .ok_or_else(|| RouteError::UnknownToken(source.to_owned()))?;
+        self.relax_from(log_negated_graph, source_idx, scorer);
+
+        // One relaxation round past the `n - 1` already done: anything still
+        // relaxable lies on, or downstream of, a negative-weight cycle
+        // reachable from `source`, where "shortest path" stops being
+        // well-defined.
+        if self.relax_round(log_negated_graph, scorer) {
+            return Err(RouteError::NegativeCycle);
         }
 
-        let mut arbitrage: Vec<String> = arbitrage_paths.into_iter().collect();
-        arbitrage.sort();
-        Trades { arbitrage }
+        Ok(())
     }
+
+    /// Reconstructs the path from `compute_shortest_paths`'s `source` to
+    /// `destination_idx` by walking `pre` backwards. Returns `None` if
+    /// `destination_idx` was never relaxed, i.e. is unreachable from that
+    /// source.
+    fn path_to(&self, destination_idx: usize) -> Option<Vec<&'a String>> {
+        if self.min_dist[destination_idx] == f64::MAX {
+            return None;
+        }
+
+        let mut hops = vec![destination_idx];
+        while self.pre[*hops.last().unwrap()] != -1 {
+            hops.push(self.pre[*hops.last().unwrap()] as usize);
+        }
+        hops.reverse();
+
+        Some(hops.into_iter().map(|idx| self.indices_map[idx]).collect())
+    }
+}
+
+/// Scores a directed edge `first_token -> second_token`, given its
+/// fee-adjusted log-weight, as the cost used by `best_route`'s search.
+/// Pluggable so callers can optimize for something other than raw log-rate
+/// (e.g. penalizing low-liquidity hops) without touching the search itself.
+pub type Scorer<'a> = dyn Fn(&str, &str, f64) -> f64 + 'a;
+
+/// The default scorer: uses the fee-adjusted log-weight unchanged, i.e.
+/// optimizes for net output exactly as `log_negate` computed it.
+pub fn default_scorer(_first_token: &str, _second_token: &str, weight: f64) -> f64 {
+    weight
+}
+
+#[derive(Error, Debug)]
+pub enum RouteError {
+    #[error("unknown token `{0}`")]
+    UnknownToken(String),
+    #[error("graph contains a negative-weight cycle; refusing to route through it")]
+    NegativeCycle,
+    #[error("no route found from `{0}` to `{1}`")]
+    NoPath(String, String),
+}
+
+#[derive(Debug)]
+pub struct Route {
+    pub hops: Vec<String>,
+    pub realized_amount: f64,
+}
+
+/// Finds the maximum-output route from `source` to `destination` via
+/// `ArbitrageIteration::compute_shortest_paths`, a single-source
+/// Bellman-Ford relaxation over the log-negated graph rooted at `source`.
+///
+/// This graph can have negative edge weights in the ordinary course of
+/// things (a genuine arbitrage opportunity *is* one), so Dijkstra is not an
+/// option: it assumes non-negative weights and would silently stop
+/// exploring a cheaper path through a negative edge once a non-negative
+/// alternative had already been settled. Bellman-Ford handles negative
+/// edges correctly and only refuses to route when a negative-weight cycle
+/// is actually reachable from `source`, where "shortest path" is undefined.
+#[allow(clippy::too_many_arguments)]
+pub fn best_route(
+    log_negated_graph: &HashMap<String, HashMap<String, f64>>,
+    source: &str,
+    destination: &str,
+    trade_amount: u64,
+    graph: &Graph,
+    fees: &FeeConfig,
+    scorer: &Scorer,
+) -> Result<Route, RouteError> {
+    let mut arb_iter = ArbitrageIteration::new(log_negated_graph.keys().len());
+    arb_iter.compute_shortest_paths(log_negated_graph, source, scorer)?;
+
+    let destination_idx = arb_iter
+        .token_index(destination)
+        .ok_or_else(|| RouteError::UnknownToken(destination.to_owned()))?;
+    let hops = arb_iter
+        .path_to(destination_idx)
+        .ok_or_else(|| RouteError::NoPath(source.to_owned(), destination.to_owned()))?;
+
+    let realized_amount = exact_new_amount(&hops, graph, trade_amount, fees)
+        .0
+        .to_f64()
+        .unwrap_or(0.0);
+
+    Ok(Route {
+        hops: hops.into_iter().cloned().collect(),
+        realized_amount,
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    use super::ArbitrageIteration;
-    use crate::pairs::PairMap;
+    use super::{
+        best_route, collapse_pools, default_scorer, dfs_trades, optimal_trade, ArbitrageIteration,
+        RouteError, DEFAULT_AMM_GAMMA,
+    };
+    use crate::pairs::{FeeConfig, PairMap};
     use maplit::hashmap;
 
-    #[test]
-    fn challenge_example() {
-        let pair_map = PairMap::from(hashmap! {
+    /// A sparse, non-complete graph (unlike `challenge_pair_map`, most token
+    /// pairs here have no rate at all) with one outlier-favorable hop:
+    /// `S -> T` costs 5, `S -> V` costs 6, but `V -> T` costs -100, so the
+    /// cheapest route from `S` to `T` goes through `V` even though it isn't
+    /// the lowest-cost first hop. `W` is unreachable from `S`. Self-pairs
+    /// (mirroring the challenge API's convention, see `challenge_pair_map`)
+    /// keep every token a valid node even where it has no other outgoing
+    /// pair.
+    fn sparse_negative_edge_pair_map() -> PairMap {
+        PairMap::from(hashmap! {
+            "S-S".to_owned() => "1.00000000".to_owned(),
+            "S-T".to_owned() => "0.03125000".to_owned(),
+            "S-V".to_owned() => "0.01562500".to_owned(),
+            "T-T".to_owned() => "1.00000000".to_owned(),
+            "V-V".to_owned() => "1.00000000".to_owned(),
+            "V-T".to_owned() => "1267650600228229401496703205376".to_owned(),
+            "W-W".to_owned() => "1.00000000".to_owned(),
+            "W-S".to_owned() => "1.00000000".to_owned(),
+        })
+    }
+
+    fn challenge_pair_map() -> PairMap {
+        PairMap::from(hashmap! {
             "BTC-BTC".to_owned() => "1.00000000".to_owned(),
             "BTC-BORG".to_owned() => "116352.26544401".to_owned(),
             "BTC-DAI".to_owned() => "23524.13915530".to_owned(),
@@ -174,21 +675,193 @@ mod tests {
             "EUR-BORG".to_owned() => "5.04275777".to_owned(),
             "EUR-DAI".to_owned() => "1.02113789".to_owned(),
             "EUR-EUR".to_owned() => "1.00000000".to_owned()
-        });
+        })
+    }
 
-        let graph = pair_map.to_graph().unwrap();
-        let log_negated_graph = graph.log_negate();
+    #[test]
+    fn challenge_example() {
+        let graph = challenge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
 
         let tokens_count = graph.as_ref().keys().len();
         let mut arb_iter = ArbitrageIteration::new(tokens_count);
         arb_iter.compute_arbitrage_opportunities(&log_negated_graph);
-        let trades = arb_iter.trades(&log_negated_graph, 100, graph.as_ref());
-
-        assert_eq!(vec![
-            "Arbitrage opportunity: BORG <--> EUR <--> DAI <--> BORG, new trade amount is 101.60928773",
-            "Arbitrage opportunity: BTC <--> EUR <--> DAI <--> BTC, new trade amount is 101.88977518",
-            "Arbitrage opportunity: DAI <--> EUR <--> DAI, new trade amount is 101.17078960",
-            "Arbitrage opportunity: EUR <--> DAI <--> EUR, new trade amount is 101.17078960",
-        ], trades.arbitrage);
+        let trades = arb_iter.trades(&log_negated_graph, 100, &graph, &fees);
+
+        // Every distinct negative-weight cycle up to length `n`, not just the
+        // one cycle a single Bellman-Ford predecessor tree happens to land on
+        // (see `ArbitrageIteration::trades`).
+        assert_eq!(
+            vec![
+                "Arbitrage opportunity: BORG <--> BTC <--> BORG, new trade amount is 100.99376641",
+                "Arbitrage opportunity: BORG <--> BTC <--> DAI <--> BORG, new trade amount is 100.70715990",
+                "Arbitrage opportunity: BORG <--> BTC <--> DAI <--> EUR <--> BORG, new trade amount is 102.01694651",
+                "Arbitrage opportunity: BORG <--> BTC <--> EUR <--> BORG, new trade amount is 101.80679203",
+                "Arbitrage opportunity: BORG <--> BTC <--> EUR <--> DAI <--> BORG, new trade amount is 101.67634366",
+                "Arbitrage opportunity: BORG <--> DAI <--> BORG, new trade amount is 101.30370145",
+                "Arbitrage opportunity: BORG <--> DAI <--> BTC <--> BORG, new trade amount is 102.52518614",
+                "Arbitrage opportunity: BORG <--> DAI <--> BTC <--> EUR <--> BORG, new trade amount is 103.35054009",
+                "Arbitrage opportunity: BORG <--> DAI <--> EUR <--> BORG, new trade amount is 102.62124662",
+                "Arbitrage opportunity: BORG <--> DAI <--> EUR <--> BTC <--> BORG, new trade amount is 103.11745405",
+                "Arbitrage opportunity: BORG <--> EUR <--> BORG, new trade amount is 101.73965007",
+                "Arbitrage opportunity: BORG <--> EUR <--> BTC <--> BORG, new trade amount is 102.23159469",
+                "Arbitrage opportunity: BORG <--> EUR <--> BTC <--> DAI <--> BORG, new trade amount is 101.94147540",
+                "Arbitrage opportunity: BORG <--> EUR <--> DAI <--> BORG, new trade amount is 101.60928773",
+                "Arbitrage opportunity: BORG <--> EUR <--> DAI <--> BTC <--> BORG, new trade amount is 102.83445706",
+                "Arbitrage opportunity: BTC <--> DAI <--> BTC, new trade amount is 100.91855698",
+                "Arbitrage opportunity: BTC <--> DAI <--> EUR <--> BTC, new trade amount is 101.50154371",
+                "Arbitrage opportunity: BTC <--> EUR <--> BTC, new trade amount is 101.29245096",
+                "Arbitrage opportunity: BTC <--> EUR <--> DAI <--> BTC, new trade amount is 101.88977518",
+                "Arbitrage opportunity: DAI <--> EUR <--> DAI, new trade amount is 101.17078960",
+            ],
+            trades.arbitrage
+        );
+    }
+
+    #[test]
+    fn dfs_finds_every_two_hop_cycle() {
+        let graph = challenge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let trades = dfs_trades(&log_negated_graph, 2, 100, &graph, &fees);
+
+        assert_eq!(
+            vec![
+                "Arbitrage opportunity: BORG <--> BTC <--> BORG, new trade amount is 100.99376641",
+                "Arbitrage opportunity: BORG <--> DAI <--> BORG, new trade amount is 101.30370145",
+                "Arbitrage opportunity: BORG <--> EUR <--> BORG, new trade amount is 101.73965007",
+                "Arbitrage opportunity: BTC <--> DAI <--> BTC, new trade amount is 100.91855698",
+                "Arbitrage opportunity: BTC <--> EUR <--> BTC, new trade amount is 101.29245096",
+                "Arbitrage opportunity: DAI <--> EUR <--> DAI, new trade amount is 101.17078960",
+            ],
+            trades.arbitrage
+        );
+    }
+
+    #[test]
+    fn collapse_pools_single_pool_returns_its_reserves_unchanged() {
+        assert_eq!(
+            collapse_pools(&[(100.0, 200.0)], DEFAULT_AMM_GAMMA),
+            Some((100.0, 200.0))
+        );
+    }
+
+    #[test]
+    fn collapse_pools_folds_a_chain_left_to_right() {
+        let (e_in, e_out) =
+            collapse_pools(&[(100.0, 200.0), (150.0, 300.0)], DEFAULT_AMM_GAMMA).unwrap();
+
+        assert!((e_in - 42.930738408700634).abs() < 1e-9);
+        assert!((e_out - 171.2077847738981).abs() < 1e-9);
+    }
+
+    #[test]
+    fn collapse_pools_rejects_a_non_positive_denominator() {
+        // Chosen so `b_prime + gamma * e_out == 0`.
+        assert_eq!(
+            collapse_pools(&[(100.0, 200.0), (-199.4, 300.0)], DEFAULT_AMM_GAMMA),
+            None
+        );
+    }
+
+    #[test]
+    fn optimal_trade_finds_the_profit_maximizing_input() {
+        let (x_star, profit) = optimal_trade(100.0, 200.0, DEFAULT_AMM_GAMMA).unwrap();
+
+        assert!((x_star - 41.33306405700183).abs() < 1e-9);
+        assert!((profit - 17.03296917787197).abs() < 1e-9);
+    }
+
+    #[test]
+    fn optimal_trade_rejects_a_non_profitable_pool() {
+        assert_eq!(optimal_trade(1000.0, 10.0, DEFAULT_AMM_GAMMA), None);
+    }
+
+    #[test]
+    fn reserve_driven_cycle_is_reported_even_at_a_breakeven_rate() {
+        // The rate alone is exactly breakeven (2.0 * 0.5 == 1.0, so the exact
+        // fee-adjusted amount never exceeds the input), so this cycle is
+        // only reported because its pools admit a profitable optimal trade.
+        let pair_map = PairMap::from(hashmap! {
+            "A-B".to_owned() => "2.00000000".to_owned(),
+            "B-A".to_owned() => "0.50000000".to_owned(),
+        })
+        .with_reserves(hashmap! {
+            "A-B".to_owned() => (100.0, 200.0),
+            "B-A".to_owned() => (150.0, 300.0),
+        });
+
+        let graph = pair_map.to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let trades = dfs_trades(&log_negated_graph, 2, 100, &graph, &fees);
+
+        assert_eq!(
+            vec![
+                "Arbitrage opportunity: A <--> B <--> A, new trade amount is 100.00000000, \
+                 optimal input is 42.80155865 with expected profit 42.54474930",
+            ],
+            trades.arbitrage
+        );
+    }
+
+    #[test]
+    fn best_route_takes_a_negative_edge_over_a_cheaper_first_hop() {
+        let graph = sparse_negative_edge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let route =
+            best_route(&log_negated_graph, "S", "T", 100, &graph, &fees, &default_scorer).unwrap();
+
+        // The direct S -> T hop costs 5, cheaper than S -> V's 6, but
+        // V -> T's -100 makes S -> V -> T the true cheapest route overall.
+        assert_eq!(
+            route.hops,
+            vec!["S".to_owned(), "V".to_owned(), "T".to_owned()]
+        );
+        let expected_amount = 1.9807040628566084e30;
+        assert!((route.realized_amount - expected_amount).abs() / expected_amount < 1e-9);
+    }
+
+    #[test]
+    fn best_route_errors_on_unreachable_destination() {
+        let graph = sparse_negative_edge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let err = best_route(&log_negated_graph, "S", "W", 100, &graph, &fees, &default_scorer)
+            .unwrap_err();
+
+        assert!(
+            matches!(err, RouteError::NoPath(source, destination) if source == "S" && destination == "W")
+        );
+    }
+
+    #[test]
+    fn best_route_errors_on_unknown_token() {
+        let graph = sparse_negative_edge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let err = best_route(&log_negated_graph, "ZZZ", "T", 100, &graph, &fees, &default_scorer)
+            .unwrap_err();
+
+        assert!(matches!(err, RouteError::UnknownToken(token) if token == "ZZZ"));
+    }
+
+    #[test]
+    fn best_route_refuses_to_route_through_a_reachable_negative_cycle() {
+        let graph = challenge_pair_map().to_graph().unwrap();
+        let fees = FeeConfig::default();
+        let log_negated_graph = graph.log_negate(&fees);
+
+        let err = best_route(&log_negated_graph, "DAI", "BTC", 100, &graph, &fees, &default_scorer)
+            .unwrap_err();
+
+        assert!(matches!(err, RouteError::NegativeCycle));
     }
 }
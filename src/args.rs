@@ -1,10 +1,83 @@
-use clap::Parser;
+use crate::pairs::FeeConfig;
+use clap::{Parser, Subcommand, ValueEnum};
 use reqwest::Url;
 
+/// Selects which solver is used to find arbitrage cycles.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SolverMode {
+    /// Bellman-Ford relaxation from every token at once (the default), used
+    /// to check whether any negative cycle exists; if one does, every cycle
+    /// up to the token count long is enumerated by DFS. Above 12 tokens the
+    /// enumeration is capped at 4 hops.
+    BellmanFord,
+    /// DFS enumeration of every cycle up to `max_hops` long. Useful when hop
+    /// count must be bounded (e.g. gas/step-sensitive on-chain arbitrage).
+    Dfs,
+}
+
 #[derive(Parser, Debug)]
 pub struct Args {
     #[arg(short, long)]
     pub url: Url,
     #[arg(short, long)]
     pub trade_amount: u64,
+    /// Proportional taker fee applied to every pair that doesn't have an
+    /// explicit `--pair-fee` override (e.g. `0.001` for 0.1%).
+    #[arg(long, default_value_t = 0.0)]
+    pub default_fee: f64,
+    /// Minimum absolute fee charged per trade, regardless of the
+    /// proportional fee, so dust-sized cycles that only clear due to
+    /// rounding are discarded.
+    #[arg(long, default_value_t = 0.0)]
+    pub min_fee: f64,
+    /// Per-pair fee override in `FROM-TO=fee` form, e.g. `BTC-EUR=0.0015`.
+    /// Can be passed multiple times.
+    #[arg(long = "pair-fee", value_parser = parse_pair_fee)]
+    pub pair_fees: Vec<(String, f64)>,
+    /// Which solver to use to find arbitrage cycles.
+    #[arg(long, value_enum, default_value_t = SolverMode::BellmanFord)]
+    pub solver: SolverMode,
+    /// Maximum cycle length (in hops) considered by the `dfs` solver. Unused
+    /// by the `bellman-ford` solver.
+    #[arg(long, default_value_t = 4)]
+    pub max_hops: usize,
+    /// Find the best-rate route between two tokens instead of scanning for
+    /// arbitrage cycles.
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Operation to run once the pairs are fetched, in place of the default
+/// arbitrage cycle scan.
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Find the maximum-output route between two tokens.
+    Route {
+        /// Token to convert from.
+        #[arg(long)]
+        from: String,
+        /// Token to convert to.
+        #[arg(long)]
+        to: String,
+    },
+}
+
+impl Args {
+    pub fn fee_config(&self) -> FeeConfig {
+        FeeConfig {
+            default_fee: self.default_fee,
+            per_pair_fees: self.pair_fees.iter().cloned().collect(),
+            min_fee: self.min_fee,
+        }
+    }
+}
+
+fn parse_pair_fee(s: &str) -> Result<(String, f64), String> {
+    let (pair, fee) = s
+        .split_once('=')
+        .ok_or_else(|| format!("invalid pair fee `{s}`, expected FROM-TO=fee"))?;
+    let fee = fee
+        .parse::<f64>()
+        .map_err(|e| format!("invalid fee value `{fee}`: {e}"))?;
+    Ok((pair.to_owned(), fee))
 }
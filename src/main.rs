@@ -1,6 +1,6 @@
 use algorithm::ArbitrageIteration;
 use anyhow::Context;
-use args::Args;
+use args::{Args, Command, SolverMode};
 use clap::Parser;
 use pairs::PairReader;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
@@ -17,6 +17,7 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let args = Args::parse();
+    let fee_config = args.fee_config();
     let pair_reader = PairReader::new(args.url);
     let pairs_map = pair_reader
         .fetch_pairs_map()
@@ -24,13 +25,50 @@ async fn main() -> anyhow::Result<()> {
         .context("failed fetching pairs map")?;
 
     let graph = pairs_map.to_graph()?;
-    let log_negated_graph = graph.log_negate();
-    let tokens_count = graph.as_ref().keys().len();
-    let mut arbitrage_iter = ArbitrageIteration::new(tokens_count);
-    arbitrage_iter.compute_arbitrage_opportunities(&log_negated_graph);
-    let trades = arbitrage_iter.trades(&log_negated_graph, args.trade_amount, graph.as_ref());
+    let log_negated_graph = graph.log_negate(&fee_config);
 
-    println!("{:#?}", trades.arbitrage);
+    match args.command {
+        Some(Command::Route { from, to }) => {
+            let route = algorithm::best_route(
+                &log_negated_graph,
+                &from,
+                &to,
+                args.trade_amount,
+                &graph,
+                &fee_config,
+                &algorithm::default_scorer,
+            )?;
+            println!(
+                "route: {}, realized amount is {:.8}",
+                route.hops.join(" <--> "),
+                route.realized_amount
+            );
+        }
+        None => {
+            let trades = match args.solver {
+                SolverMode::BellmanFord => {
+                    let tokens_count = graph.as_ref().keys().len();
+                    let mut arbitrage_iter = ArbitrageIteration::new(tokens_count);
+                    arbitrage_iter.compute_arbitrage_opportunities(&log_negated_graph);
+                    arbitrage_iter.trades(
+                        &log_negated_graph,
+                        args.trade_amount,
+                        &graph,
+                        &fee_config,
+                    )
+                }
+                SolverMode::Dfs => algorithm::dfs_trades(
+                    &log_negated_graph,
+                    args.max_hops,
+                    args.trade_amount,
+                    &graph,
+                    &fee_config,
+                ),
+            };
+
+            println!("{:#?}", trades.arbitrage);
+        }
+    }
 
     Ok(())
 }
@@ -4,10 +4,12 @@
 //! represents the pair conversion ratio.
 
 use anyhow::Context;
+use bigdecimal::{BigDecimal, ToPrimitive};
 use reqwest::{Client, Url};
 use serde::Deserialize;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::str::FromStr;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -24,37 +26,111 @@ pub type Result<T> = std::result::Result<T, Error>;
 #[derive(Deserialize, Debug)]
 pub struct PairMap {
     rates: HashMap<String, String>,
+    /// Pool reserves `(r_in, r_out)` for venues that expose them (e.g. AMMs),
+    /// keyed the same way as `rates`, i.e. `"FROM-TO"`. Absent for venues that
+    /// only publish a rate snapshot.
+    #[serde(default)]
+    reserves: HashMap<String, (f64, f64)>,
 }
 
 impl PairMap {
     #[cfg(test)]
     pub fn from(rates: HashMap<String, String>) -> Self {
-        Self { rates }
+        Self {
+            rates,
+            reserves: HashMap::new(),
+        }
+    }
+
+    #[cfg(test)]
+    pub fn with_reserves(mut self, reserves: HashMap<String, (f64, f64)>) -> Self {
+        self.reserves = reserves;
+        self
+    }
+}
+
+/// Configures the trading fees applied when building the log-negated graph
+/// and when recomputing the amount realized by a cycle. Fees are looked up
+/// per pair (keyed the same way as `PairMap::rates`, i.e. `"FROM-TO"`),
+/// falling back to `default_fee` when a pair has no override.
+#[derive(Debug, Clone)]
+pub struct FeeConfig {
+    pub default_fee: f64,
+    pub per_pair_fees: HashMap<String, f64>,
+    /// Minimum absolute fee charged per trade, regardless of the
+    /// proportional fee, so that cycles which only clear due to rounding are
+    /// discarded (mirrors the `min_tx_amount` floor used for komodo's
+    /// `dex_fee_amount`).
+    pub min_fee: f64,
+}
+
+impl Default for FeeConfig {
+    fn default() -> Self {
+        Self {
+            default_fee: 0.0,
+            per_pair_fees: HashMap::new(),
+            min_fee: 0.0,
+        }
+    }
+}
+
+impl FeeConfig {
+    pub fn fee_for(&self, first_token: &str, second_token: &str) -> f64 {
+        let key = format!("{first_token}-{second_token}");
+        *self.per_pair_fees.get(&key).unwrap_or(&self.default_fee)
     }
 }
 
 #[derive(Debug)]
-pub struct Graph(HashMap<String, HashMap<String, u64>>);
+pub struct Graph {
+    /// Exact pair conversion rates, parsed as arbitrary-precision decimals so
+    /// neither the decimal string parsing nor the downstream cycle-product
+    /// check is subject to `f64` rounding.
+    rates: HashMap<String, HashMap<String, BigDecimal>>,
+    /// Pool reserves per directed edge, present only for the pairs a venue
+    /// reports reserves for (see `PairMap::reserves`).
+    reserves: HashMap<String, HashMap<String, (f64, f64)>>,
+}
+
+impl AsRef<HashMap<String, HashMap<String, BigDecimal>>> for Graph {
+    fn as_ref(&self) -> &HashMap<String, HashMap<String, BigDecimal>> {
+        &self.rates
+    }
+}
 
-impl AsRef<HashMap<String, HashMap<String, u64>>> for Graph {
-    fn as_ref(&self) -> &HashMap<String, HashMap<String, u64>> {
-        &self.0
+impl AsMut<HashMap<String, HashMap<String, BigDecimal>>> for Graph {
+    fn as_mut(&mut self) -> &mut HashMap<String, HashMap<String, BigDecimal>> {
+        &mut self.rates
     }
 }
 
-impl AsMut<HashMap<String, HashMap<String, u64>>> for Graph {
-    fn as_mut(&mut self) -> &mut HashMap<String, HashMap<String, u64>> {
-        &mut self.0
+impl Graph {
+    /// Returns the reserves `(r_in, r_out)` of the pool backing the directed
+    /// edge `first_token -> second_token`, if the venue reported them.
+    pub fn reserves_for(&self, first_token: &str, second_token: &str) -> Option<(f64, f64)> {
+        self.reserves.get(first_token)?.get(second_token).copied()
     }
 }
 
 impl Graph {
-    pub fn log_negate(&self) -> HashMap<String, HashMap<String, f64>> {
+    /// Log-negates the rate graph so that min-cost path search can be used to
+    /// find arbitrage cycles. `fees` folds a `-log2(1 - fee)` penalty into
+    /// each edge so that a cycle is only reported as profitable once the
+    /// per-pair trading fees are accounted for.
+    ///
+    /// This is the fast, approximate path used for the Bellman-Ford
+    /// relaxation: rates are downcast to `f64` here, but the final
+    /// accept/reject decision for a discovered cycle is re-derived from the
+    /// exact `BigDecimal` rates (see `algorithm::arbitrage_message`).
+    pub fn log_negate(&self, fees: &FeeConfig) -> HashMap<String, HashMap<String, f64>> {
         let mut new_graph = HashMap::new();
         for edges in self.as_ref() {
             let mut detailed_edges = HashMap::new();
             for weight in edges.1 {
-                detailed_edges.insert(weight.0.to_owned(), -1f64 * (*weight.1 as f64).log2());
+                let fee = fees.fee_for(edges.0, weight.0);
+                let rate_weight = -1f64 * weight.1.to_f64().unwrap_or(f64::MAX).log2();
+                let fee_weight = -1f64 * (1.0 - fee).log2();
+                detailed_edges.insert(weight.0.to_owned(), rate_weight + fee_weight);
             }
             new_graph.insert(edges.0.to_owned(), detailed_edges);
         }
@@ -64,42 +140,48 @@ impl Graph {
 }
 
 impl PairMap {
-    /// Transform the map into a graph of relationships between tokens,
-    /// where edges represent the pair weight normalized to 10^8 (this is
-    /// based on the observation that the API returns all pairs weights with
-    /// 8 decimal places).
+    /// Transform the map into a graph of relationships between tokens, where
+    /// edges represent the exact pair conversion rate, parsed as an
+    /// arbitrary-precision decimal so it doesn't matter whether a rate has a
+    /// fractional part at all, how many decimals it carries, or how many
+    /// leading zeros are in the fraction.
     pub fn to_graph(&self) -> anyhow::Result<Graph> {
-        let mut graph_inner: HashMap<String, HashMap<String, u64>> = HashMap::new();
+        let mut graph_inner: HashMap<String, HashMap<String, BigDecimal>> = HashMap::new();
         for (k, v) in &self.rates {
             let tokens: Vec<&str> = k.split('-').collect();
             let first_token = tokens[0];
             let second_token = tokens[1];
-            // let v_as_f64 = v
-            //     .parse::<f64>()
-            //     .context(format!("decimals part {err_msg}"))?;
-
-            let v_parts: Vec<&str> = v.split('.').collect();
-            let v_as_u64 = v_parts[0]
-                .parse::<u64>()
-                .context("can not convert rate whole part to u64")?
-                * 100000000u64
-                + v_parts[1]
-                    .parse::<u64>()
-                    .context("can not convert rate fractional part to u64")?;
+
+            let rate = BigDecimal::from_str(v)
+                .with_context(|| format!("can not parse rate `{v}` for pair `{k}`"))?;
 
             match graph_inner.get_mut(first_token) {
                 Some(edges) => {
-                    edges.insert(second_token.to_owned(), v_as_u64);
+                    edges.insert(second_token.to_owned(), rate);
                 }
                 None => {
                     let mut new_map = HashMap::new();
-                    new_map.insert(second_token.to_owned(), v_as_u64);
+                    new_map.insert(second_token.to_owned(), rate);
                     graph_inner.insert(first_token.to_owned(), new_map);
                 }
             };
         }
 
-        Ok(Graph(graph_inner))
+        let mut reserves_inner: HashMap<String, HashMap<String, (f64, f64)>> = HashMap::new();
+        for (k, reserves) in &self.reserves {
+            let tokens: Vec<&str> = k.split('-').collect();
+            let first_token = tokens[0];
+            let second_token = tokens[1];
+            reserves_inner
+                .entry(first_token.to_owned())
+                .or_default()
+                .insert(second_token.to_owned(), *reserves);
+        }
+
+        Ok(Graph {
+            rates: graph_inner,
+            reserves: reserves_inner,
+        })
     }
 }
 